@@ -36,6 +36,9 @@ pub use self::http::start_http_thread;
 
 pub mod http;
 pub mod http_endpoint;
+pub mod request_handler;
+
+pub use self::request_handler::{dispatch_request, RecordingHandler, RequestHandler};
 
 use crate::config::{
     DeviceConfig, DiskConfig, FsConfig, NetConfig, PmemConfig, VmConfig, VsockConfig,
@@ -76,6 +79,9 @@ pub enum ApiError {
     /// The VM info is not available.
     VmInfo(VmError),
 
+    /// The VMM could not be pinged.
+    VmmPing(VmError),
+
     /// The VM config is missing.
     VmMissingConfig,
 
@@ -114,6 +120,27 @@ pub enum ApiError {
 
     /// The vsock device could not be added to the VM.
     VmAddVsock(VmError),
+
+    /// The VM could not be snapshotted.
+    VmSnapshot(VmError),
+
+    /// The VM could not be restored.
+    VmRestore(VmError),
+
+    /// The VM could not be paused.
+    VmPause(VmError),
+
+    /// The VM could not be resumed.
+    VmResume(VmError),
+
+    /// The VM could not be migrated to the destination.
+    VmSendMigration(VmError),
+
+    /// The VM could not be migrated from the source.
+    VmReceiveMigration(VmError),
+
+    /// The VM could not be resized.
+    VmResize(VmError),
 }
 pub type ApiResult<T> = std::result::Result<T, ApiError>;
 
@@ -133,6 +160,33 @@ pub struct VmRemoveDeviceData {
     pub id: String,
 }
 
+#[derive(Clone, Deserialize, Serialize)]
+pub struct VmSnapshotConfig {
+    pub destination_url: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RestoreConfig {
+    pub source_url: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct VmSendMigrationData {
+    pub destination_url: String,
+    pub local: bool,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct VmReceiveMigrationData {
+    pub receiver_url: String,
+}
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct VmResizeData {
+    pub desired_vcpus: Option<u8>,
+    pub desired_ram: Option<u64>,
+}
+
 pub enum ApiResponsePayload {
     /// No data is sent on the channel.
     Empty,
@@ -150,7 +204,8 @@ pub type ApiResponse = std::result::Result<ApiResponsePayload, ApiError>;
 #[allow(clippy::large_enum_variant)]
 pub enum ApiRequest {
     /// Create the virtual machine. This request payload is a VM configuration
-    /// (VmConfig).
+    /// (VmConfig). A "pty" console/serial output mode is not yet a
+    /// VmConfig variant in this tree, so it cannot be requested here yet.
     /// If the VMM API server could not create the VM, it will send a VmCreate
     /// error back.
     VmCreate(Arc<Mutex<VmConfig>>, Sender<ApiResponse>),
@@ -207,6 +262,27 @@ pub enum ApiRequest {
 
     /// Add a vsock device to the VM.
     VmAddVsock(Arc<VsockConfig>, Sender<ApiResponse>),
+
+    /// Take a snapshot of the current VM.
+    VmSnapshot(Arc<VmSnapshotConfig>, Sender<ApiResponse>),
+
+    /// Restore a VM from a snapshot.
+    VmRestore(Arc<RestoreConfig>, Sender<ApiResponse>),
+
+    /// Pause a VM.
+    VmPause(Sender<ApiResponse>),
+
+    /// Resume a VM.
+    VmResume(Sender<ApiResponse>),
+
+    /// Send a VM migration to a destination URL.
+    VmSendMigration(Arc<VmSendMigrationData>, Sender<ApiResponse>),
+
+    /// Receive a VM migration from a source, listening on a URL.
+    VmReceiveMigration(Arc<VmReceiveMigrationData>, Sender<ApiResponse>),
+
+    /// Resize the VM.
+    VmResize(Arc<VmResizeData>, Sender<ApiResponse>),
 }
 
 pub fn vm_create(
@@ -242,6 +318,33 @@ pub enum VmAction {
 
     /// Reboot a VM
     Reboot,
+
+    /// Pause a VM
+    Pause,
+
+    /// Resume a VM
+    Resume,
+
+    /// Add a device to the VM
+    AddDevice(Arc<DeviceConfig>),
+
+    /// Remove a device from the VM
+    RemoveDevice(Arc<VmRemoveDeviceData>),
+
+    /// Add a disk to the VM
+    AddDisk(Arc<DiskConfig>),
+
+    /// Add a fs to the VM
+    AddFs(Arc<FsConfig>),
+
+    /// Add a pmem device to the VM
+    AddPmem(Arc<PmemConfig>),
+
+    /// Add a network device to the VM
+    AddNet(Arc<NetConfig>),
+
+    /// Add a vsock device to the VM
+    AddVsock(Arc<VsockConfig>),
 }
 
 fn vm_action(api_evt: EventFd, api_sender: Sender<ApiRequest>, action: VmAction) -> ApiResult<()> {
@@ -252,6 +355,15 @@ fn vm_action(api_evt: EventFd, api_sender: Sender<ApiRequest>, action: VmAction)
         VmAction::Delete => ApiRequest::VmDelete(response_sender),
         VmAction::Shutdown => ApiRequest::VmShutdown(response_sender),
         VmAction::Reboot => ApiRequest::VmReboot(response_sender),
+        VmAction::Pause => ApiRequest::VmPause(response_sender),
+        VmAction::Resume => ApiRequest::VmResume(response_sender),
+        VmAction::AddDevice(data) => ApiRequest::VmAddDevice(data, response_sender),
+        VmAction::RemoveDevice(data) => ApiRequest::VmRemoveDevice(data, response_sender),
+        VmAction::AddDisk(data) => ApiRequest::VmAddDisk(data, response_sender),
+        VmAction::AddFs(data) => ApiRequest::VmAddFs(data, response_sender),
+        VmAction::AddPmem(data) => ApiRequest::VmAddPmem(data, response_sender),
+        VmAction::AddNet(data) => ApiRequest::VmAddNet(data, response_sender),
+        VmAction::AddVsock(data) => ApiRequest::VmAddVsock(data, response_sender),
     };
 
     // Send the VM request.
@@ -279,6 +391,14 @@ pub fn vm_reboot(api_evt: EventFd, api_sender: Sender<ApiRequest>) -> ApiResult<
     vm_action(api_evt, api_sender, VmAction::Reboot)
 }
 
+pub fn vm_pause(api_evt: EventFd, api_sender: Sender<ApiRequest>) -> ApiResult<()> {
+    vm_action(api_evt, api_sender, VmAction::Pause)
+}
+
+pub fn vm_resume(api_evt: EventFd, api_sender: Sender<ApiRequest>) -> ApiResult<()> {
+    vm_action(api_evt, api_sender, VmAction::Resume)
+}
+
 pub fn vm_info(api_evt: EventFd, api_sender: Sender<ApiRequest>) -> ApiResult<VmInfo> {
     let (response_sender, response_receiver) = channel();
 
@@ -331,17 +451,7 @@ pub fn vm_add_device(
     api_sender: Sender<ApiRequest>,
     data: Arc<DeviceConfig>,
 ) -> ApiResult<()> {
-    let (response_sender, response_receiver) = channel();
-
-    // Send the VM add-device request.
-    api_sender
-        .send(ApiRequest::VmAddDevice(data, response_sender))
-        .map_err(ApiError::RequestSend)?;
-    api_evt.write(1).map_err(ApiError::EventFdWrite)?;
-
-    response_receiver.recv().map_err(ApiError::ResponseRecv)??;
-
-    Ok(())
+    vm_action(api_evt, api_sender, VmAction::AddDevice(data))
 }
 
 pub fn vm_remove_device(
@@ -349,29 +459,59 @@ pub fn vm_remove_device(
     api_sender: Sender<ApiRequest>,
     data: Arc<VmRemoveDeviceData>,
 ) -> ApiResult<()> {
-    let (response_sender, response_receiver) = channel();
+    vm_action(api_evt, api_sender, VmAction::RemoveDevice(data))
+}
 
-    // Send the VM remove-device request.
-    api_sender
-        .send(ApiRequest::VmRemoveDevice(data, response_sender))
-        .map_err(ApiError::RequestSend)?;
-    api_evt.write(1).map_err(ApiError::EventFdWrite)?;
+pub fn vm_add_disk(
+    api_evt: EventFd,
+    api_sender: Sender<ApiRequest>,
+    data: Arc<DiskConfig>,
+) -> ApiResult<()> {
+    vm_action(api_evt, api_sender, VmAction::AddDisk(data))
+}
 
-    response_receiver.recv().map_err(ApiError::ResponseRecv)??;
+pub fn vm_add_fs(
+    api_evt: EventFd,
+    api_sender: Sender<ApiRequest>,
+    data: Arc<FsConfig>,
+) -> ApiResult<()> {
+    vm_action(api_evt, api_sender, VmAction::AddFs(data))
+}
 
-    Ok(())
+pub fn vm_add_pmem(
+    api_evt: EventFd,
+    api_sender: Sender<ApiRequest>,
+    data: Arc<PmemConfig>,
+) -> ApiResult<()> {
+    vm_action(api_evt, api_sender, VmAction::AddPmem(data))
 }
 
-pub fn vm_add_disk(
+pub fn vm_add_net(
     api_evt: EventFd,
     api_sender: Sender<ApiRequest>,
-    data: Arc<DiskConfig>,
+    data: Arc<NetConfig>,
+) -> ApiResult<()> {
+    vm_action(api_evt, api_sender, VmAction::AddNet(data))
+}
+
+pub fn vm_add_vsock(
+    api_evt: EventFd,
+    api_sender: Sender<ApiRequest>,
+    data: Arc<VsockConfig>,
+) -> ApiResult<()> {
+    vm_action(api_evt, api_sender, VmAction::AddVsock(data))
+}
+
+pub fn vm_snapshot(
+    api_evt: EventFd,
+    api_sender: Sender<ApiRequest>,
+    data: Arc<VmSnapshotConfig>,
 ) -> ApiResult<()> {
     let (response_sender, response_receiver) = channel();
 
-    // Send the VM add-disk request.
+    // Send the VM snapshot request.
     api_sender
-        .send(ApiRequest::VmAddDisk(data, response_sender))
+        .send(ApiRequest::VmSnapshot(data, response_sender))
         .map_err(ApiError::RequestSend)?;
     api_evt.write(1).map_err(ApiError::EventFdWrite)?;
 
@@ -380,16 +520,16 @@ pub fn vm_add_disk(
     Ok(())
 }
 
-pub fn vm_add_fs(
+pub fn vm_restore(
     api_evt: EventFd,
     api_sender: Sender<ApiRequest>,
-    data: Arc<FsConfig>,
+    data: Arc<RestoreConfig>,
 ) -> ApiResult<()> {
     let (response_sender, response_receiver) = channel();
 
-    // Send the VM add-fs request.
+    // Send the VM restore request.
     api_sender
-        .send(ApiRequest::VmAddFs(data, response_sender))
+        .send(ApiRequest::VmRestore(data, response_sender))
         .map_err(ApiError::RequestSend)?;
     api_evt.write(1).map_err(ApiError::EventFdWrite)?;
 
@@ -398,16 +538,16 @@ pub fn vm_add_fs(
     Ok(())
 }
 
-pub fn vm_add_pmem(
+pub fn vm_send_migration(
     api_evt: EventFd,
     api_sender: Sender<ApiRequest>,
-    data: Arc<PmemConfig>,
+    data: Arc<VmSendMigrationData>,
 ) -> ApiResult<()> {
     let (response_sender, response_receiver) = channel();
 
-    // Send the VM add-pmem request.
+    // Send the VM send-migration request.
     api_sender
-        .send(ApiRequest::VmAddPmem(data, response_sender))
+        .send(ApiRequest::VmSendMigration(data, response_sender))
         .map_err(ApiError::RequestSend)?;
     api_evt.write(1).map_err(ApiError::EventFdWrite)?;
 
@@ -416,16 +556,16 @@ pub fn vm_add_pmem(
     Ok(())
 }
 
-pub fn vm_add_net(
+pub fn vm_receive_migration(
     api_evt: EventFd,
     api_sender: Sender<ApiRequest>,
-    data: Arc<NetConfig>,
+    data: Arc<VmReceiveMigrationData>,
 ) -> ApiResult<()> {
     let (response_sender, response_receiver) = channel();
 
-    // Send the VM add-net request.
+    // Send the VM receive-migration request.
     api_sender
-        .send(ApiRequest::VmAddNet(data, response_sender))
+        .send(ApiRequest::VmReceiveMigration(data, response_sender))
         .map_err(ApiError::RequestSend)?;
     api_evt.write(1).map_err(ApiError::EventFdWrite)?;
 
@@ -434,16 +574,16 @@ pub fn vm_add_net(
     Ok(())
 }
 
-pub fn vm_add_vsock(
+pub fn vm_resize(
     api_evt: EventFd,
     api_sender: Sender<ApiRequest>,
-    data: Arc<VsockConfig>,
+    data: Arc<VmResizeData>,
 ) -> ApiResult<()> {
     let (response_sender, response_receiver) = channel();
 
-    // Send the VM add-vsock request.
+    // Send the VM resize request.
     api_sender
-        .send(ApiRequest::VmAddVsock(data, response_sender))
+        .send(ApiRequest::VmResize(data, response_sender))
         .map_err(ApiError::RequestSend)?;
     api_evt.write(1).map_err(ApiError::EventFdWrite)?;
 
@@ -0,0 +1,389 @@
+// Copyright © 2019 Intel Corporation
+//
+// SPDX-License-Identifier: Apache-2.0
+//
+
+//! `RequestHandler` decouples the [`ApiRequest`](super::ApiRequest) plumbing
+//! from the concrete VMM implementation that eventually services each
+//! request.
+//!
+//! Every `ApiRequest` variant has a matching method here, expressed in terms
+//! of plain arguments and a `Result<_, VmError>` rather than the channel
+//! machinery the request itself carries. [`dispatch_request`] is the dynamic
+//! dispatch point: it turns an `ApiRequest` into the matching trait call and
+//! sends the `ApiResponse` back down the request's own channel. The real VMM
+//! implements this trait against its running `Vm`/`Vmm` state; [`RecordingHandler`]
+//! implements it against an in-memory call log, so a fuzz target can drive
+//! `dispatch_request` without ever touching a hypervisor.
+
+use super::{ApiError, ApiRequest, ApiResponsePayload, VmInfo, VmmPingResponse};
+use crate::config::{
+    DeviceConfig, DiskConfig, FsConfig, NetConfig, PmemConfig, VmConfig, VsockConfig,
+};
+use crate::vm::{Error as VmError, VmState};
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
+
+pub trait RequestHandler {
+    fn vm_create(&mut self, config: Arc<Mutex<VmConfig>>) -> Result<(), VmError>;
+
+    fn vm_boot(&mut self) -> Result<(), VmError>;
+
+    fn vm_delete(&mut self) -> Result<(), VmError>;
+
+    fn vm_shutdown(&mut self) -> Result<(), VmError>;
+
+    fn vm_reboot(&mut self) -> Result<(), VmError>;
+
+    fn vm_pause(&mut self) -> Result<(), VmError>;
+
+    fn vm_resume(&mut self) -> Result<(), VmError>;
+
+    fn vm_info(&self) -> Result<VmInfo, VmError>;
+
+    fn vmm_ping(&self) -> Result<VmmPingResponse, VmError>;
+
+    fn vmm_shutdown(&mut self) -> Result<(), VmError>;
+
+    fn vm_add_device(&mut self, device_cfg: Arc<DeviceConfig>) -> Result<(), VmError>;
+
+    fn vm_remove_device(&mut self, id: String) -> Result<(), VmError>;
+
+    fn vm_add_disk(&mut self, disk_cfg: Arc<DiskConfig>) -> Result<(), VmError>;
+
+    fn vm_add_fs(&mut self, fs_cfg: Arc<FsConfig>) -> Result<(), VmError>;
+
+    fn vm_add_pmem(&mut self, pmem_cfg: Arc<PmemConfig>) -> Result<(), VmError>;
+
+    fn vm_add_net(&mut self, net_cfg: Arc<NetConfig>) -> Result<(), VmError>;
+
+    fn vm_add_vsock(&mut self, vsock_cfg: Arc<VsockConfig>) -> Result<(), VmError>;
+
+    fn vm_snapshot(&mut self, destination_url: &str) -> Result<(), VmError>;
+
+    fn vm_restore(&mut self, source_url: &str) -> Result<(), VmError>;
+
+    fn vm_send_migration(&mut self, destination_url: &str, local: bool) -> Result<(), VmError>;
+
+    fn vm_receive_migration(&mut self, receiver_url: &str) -> Result<(), VmError>;
+
+    fn vm_resize(
+        &mut self,
+        desired_vcpus: Option<u8>,
+        desired_ram: Option<u64>,
+    ) -> Result<(), VmError>;
+}
+
+/// Turn an `ApiRequest` into the matching `RequestHandler` call and send the
+/// result back down the request's own response channel. This is the dynamic
+/// dispatch point that used to be a per-caller match block in the VMM thread.
+pub fn dispatch_request(handler: &mut dyn RequestHandler, request: ApiRequest) {
+    match request {
+        ApiRequest::VmCreate(config, sender) => {
+            let result = handler
+                .vm_create(config)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmCreate);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmBoot(sender) => {
+            let result = handler
+                .vm_boot()
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmBoot);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmDelete(sender) => {
+            let result = handler
+                .vm_delete()
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmDelete);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmInfo(sender) => {
+            let result = handler
+                .vm_info()
+                .map(ApiResponsePayload::VmInfo)
+                .map_err(ApiError::VmInfo);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmmPing(sender) => {
+            let result = handler
+                .vmm_ping()
+                .map(ApiResponsePayload::VmmPing)
+                .map_err(ApiError::VmmPing);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmShutdown(sender) => {
+            let result = handler
+                .vm_shutdown()
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmShutdown);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmReboot(sender) => {
+            let result = handler
+                .vm_reboot()
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmReboot);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmmShutdown(sender) => {
+            let result = handler
+                .vmm_shutdown()
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmmShutdown);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmAddDevice(device_cfg, sender) => {
+            let result = handler
+                .vm_add_device(device_cfg)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmAddDevice);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmRemoveDevice(data, sender) => {
+            let result = handler
+                .vm_remove_device(data.id.clone())
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmRemoveDevice);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmAddDisk(disk_cfg, sender) => {
+            let result = handler
+                .vm_add_disk(disk_cfg)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmAddDisk);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmAddFs(fs_cfg, sender) => {
+            let result = handler
+                .vm_add_fs(fs_cfg)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmAddFs);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmAddPmem(pmem_cfg, sender) => {
+            let result = handler
+                .vm_add_pmem(pmem_cfg)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmAddPmem);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmAddNet(net_cfg, sender) => {
+            let result = handler
+                .vm_add_net(net_cfg)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmAddNet);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmAddVsock(vsock_cfg, sender) => {
+            let result = handler
+                .vm_add_vsock(vsock_cfg)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmAddVsock);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmSnapshot(config, sender) => {
+            let result = handler
+                .vm_snapshot(&config.destination_url)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmSnapshot);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmRestore(config, sender) => {
+            let result = handler
+                .vm_restore(&config.source_url)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmRestore);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmPause(sender) => {
+            let result = handler
+                .vm_pause()
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmPause);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmResume(sender) => {
+            let result = handler
+                .vm_resume()
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmResume);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmSendMigration(data, sender) => {
+            let result = handler
+                .vm_send_migration(&data.destination_url, data.local)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmSendMigration);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmReceiveMigration(data, sender) => {
+            let result = handler
+                .vm_receive_migration(&data.receiver_url)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmReceiveMigration);
+            let _ = sender.send(result);
+        }
+        ApiRequest::VmResize(data, sender) => {
+            let result = handler
+                .vm_resize(data.desired_vcpus, data.desired_ram)
+                .map(|_| ApiResponsePayload::Empty)
+                .map_err(ApiError::VmResize);
+            let _ = sender.send(result);
+        }
+    }
+}
+
+/// A `RequestHandler` that records every call it receives instead of acting
+/// on a real `Vm`. Driving [`dispatch_request`] against this handler exercises
+/// the whole `ApiRequest` parsing and dispatch path (e.g. from a fuzzed
+/// `micro_http::Request`) without spinning up a hypervisor.
+pub struct RecordingHandler {
+    pub calls: RefCell<Vec<&'static str>>,
+    config: RefCell<Arc<Mutex<VmConfig>>>,
+    state: RefCell<VmState>,
+}
+
+impl RecordingHandler {
+    /// `initial_config` stands in for whatever the harness would otherwise
+    /// get back from a successful `vm_create`, so `vm_info` has something to
+    /// report even if it is driven before a create call lands.
+    pub fn new(initial_config: Arc<Mutex<VmConfig>>) -> Self {
+        RecordingHandler {
+            calls: RefCell::new(Vec::new()),
+            config: RefCell::new(initial_config),
+            state: RefCell::new(VmState::Shutdown),
+        }
+    }
+}
+
+impl RequestHandler for RecordingHandler {
+    fn vm_create(&mut self, config: Arc<Mutex<VmConfig>>) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_create");
+        *self.config.get_mut() = config;
+        *self.state.get_mut() = VmState::Created;
+        Ok(())
+    }
+
+    fn vm_boot(&mut self) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_boot");
+        *self.state.get_mut() = VmState::Running;
+        Ok(())
+    }
+
+    fn vm_delete(&mut self) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_delete");
+        *self.state.get_mut() = VmState::Shutdown;
+        Ok(())
+    }
+
+    fn vm_shutdown(&mut self) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_shutdown");
+        *self.state.get_mut() = VmState::Shutdown;
+        Ok(())
+    }
+
+    fn vm_reboot(&mut self) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_reboot");
+        *self.state.get_mut() = VmState::Running;
+        Ok(())
+    }
+
+    fn vm_pause(&mut self) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_pause");
+        *self.state.get_mut() = VmState::Paused;
+        Ok(())
+    }
+
+    fn vm_resume(&mut self) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_resume");
+        *self.state.get_mut() = VmState::Running;
+        Ok(())
+    }
+
+    fn vm_info(&self) -> Result<VmInfo, VmError> {
+        self.calls.borrow_mut().push("vm_info");
+        Ok(VmInfo {
+            config: self.config.borrow().clone(),
+            state: *self.state.borrow(),
+        })
+    }
+
+    fn vmm_ping(&self) -> Result<VmmPingResponse, VmError> {
+        self.calls.borrow_mut().push("vmm_ping");
+        Ok(VmmPingResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        })
+    }
+
+    fn vmm_shutdown(&mut self) -> Result<(), VmError> {
+        self.calls.get_mut().push("vmm_shutdown");
+        Ok(())
+    }
+
+    fn vm_add_device(&mut self, _device_cfg: Arc<DeviceConfig>) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_add_device");
+        Ok(())
+    }
+
+    fn vm_remove_device(&mut self, _id: String) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_remove_device");
+        Ok(())
+    }
+
+    fn vm_add_disk(&mut self, _disk_cfg: Arc<DiskConfig>) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_add_disk");
+        Ok(())
+    }
+
+    fn vm_add_fs(&mut self, _fs_cfg: Arc<FsConfig>) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_add_fs");
+        Ok(())
+    }
+
+    fn vm_add_pmem(&mut self, _pmem_cfg: Arc<PmemConfig>) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_add_pmem");
+        Ok(())
+    }
+
+    fn vm_add_net(&mut self, _net_cfg: Arc<NetConfig>) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_add_net");
+        Ok(())
+    }
+
+    fn vm_add_vsock(&mut self, _vsock_cfg: Arc<VsockConfig>) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_add_vsock");
+        Ok(())
+    }
+
+    fn vm_snapshot(&mut self, _destination_url: &str) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_snapshot");
+        Ok(())
+    }
+
+    fn vm_restore(&mut self, _source_url: &str) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_restore");
+        Ok(())
+    }
+
+    fn vm_send_migration(&mut self, _destination_url: &str, _local: bool) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_send_migration");
+        Ok(())
+    }
+
+    fn vm_receive_migration(&mut self, _receiver_url: &str) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_receive_migration");
+        Ok(())
+    }
+
+    fn vm_resize(
+        &mut self,
+        _desired_vcpus: Option<u8>,
+        _desired_ram: Option<u64>,
+    ) -> Result<(), VmError> {
+        self.calls.get_mut().push("vm_resize");
+        Ok(())
+    }
+}